@@ -1,6 +1,9 @@
 use std::cmp::min;
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
 use std::ops::Index;
 use std::slice::SliceIndex;
+use std::cmp::Reverse;
 use crate::types::Result;
 use crate::errors;
 use crate::util::check_argument;
@@ -18,7 +21,9 @@ use crate::common::cluster::{Center, multi_cluster_as_weighted_obj, multi_cluste
 /// 1. It uses an initial sampling which serves as a basis of efficiency as well as denoising, borrowing from
 /// https://en.wikipedia.org/wiki/CURE_algorithm, in that algorithm's robustness to outliers.
 /// 2. It uses a sampling mechanism to initialize some clusters based on https://en.wikipedia.org/wiki/Data_stream_clustering
-/// where the radom sampling achieves half of the the same effects as hierarchical compression.
+/// where the radom sampling achieves half of the the same effects as hierarchical compression. As an alternative,
+/// a D^2 (k-means++ style) seeding mode can be requested via SeedMode, which spreads the initial centers out
+/// against the distance function instead of picking them purely weight-proportionally.
 ///3.  It repeatedly merges the most overlapping clusters, failing that, eliminates the least weighted cluster to achieve
 /// the same effect as hieararchical compression.
 ///
@@ -41,6 +46,333 @@ const UPPER_FRACTION : f64 = 0.9;
 
 const LOWER_FRACTION : f64 = 0.1;
 
+// fixed seed for the reservoir down-sampling, so that summarize()/multi_summarize_ref()
+// are reproducible across repeated calls on the same input
+const RESERVOIR_SEED: u64 = 1363;
+
+/// Neumaier compensated summation: sums a sequence of f64 values while tracking
+/// a running compensation term so that long streams of mixed-magnitude values
+/// (e.g. weights) do not lose precision to naive accumulation.
+fn neumaier_sum<I: Iterator<Item = f64>>(values: I) -> f64 {
+    let mut sum = 0.0f64;
+    let mut compensation = 0.0f64;
+    for v in values {
+        let t = sum + v;
+        if sum.abs() >= v.abs() {
+            compensation += (sum - t) + v;
+        } else {
+            compensation += (v - t) + sum;
+        }
+        sum = t;
+    }
+    sum + compensation
+}
+
+// an entry in the reservoir min-heap, ordered by its A-ExpJ key
+struct ReservoirEntry {
+    key: f64,
+    index: usize,
+}
+
+impl PartialEq for ReservoirEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key == other.key
+    }
+}
+
+impl Eq for ReservoirEntry {}
+
+impl PartialOrd for ReservoirEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        self.key.partial_cmp(&other.key)
+    }
+}
+
+impl Ord for ReservoirEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.partial_cmp(other).unwrap()
+    }
+}
+
+/// Weighted reservoir sampling without replacement, the A-ExpJ algorithm of
+/// Efraimidis and Spirakis: selects `size` indices out of `weights.len()` with
+/// inclusion probability proportional to weight, in a single O(n log size) pass.
+/// Returns all indices unchanged if there are not more than `size` of them, or if `size` is 0.
+fn weighted_reservoir_sample(weights: &[f32], size: usize, rng: &mut ChaCha20Rng) -> Vec<usize> {
+    let n = weights.len();
+    if n <= size || size == 0 {
+        return (0..n).collect();
+    }
+
+    let key_of = |w: f32, u: f64| -> f64 {
+        let w = (w as f64).max(f64::MIN_POSITIVE);
+        u.powf(1.0 / w)
+    };
+
+    // key_of(w, u) = u^(1/w) rounds up to exactly 1.0 in f64 once w is large enough (a
+    // perfectly valid, finite f32 weight), so the min key of the heap, and the
+    // t_w = min_key^w bound derived from it, must be pulled back below 1.0 before they are
+    // used as the base of a log() or as a gen_range(t_w..1.0) bound, or a large-weight
+    // input can hand gen_range an empty range and panic
+    let clamp_key = |key: f64| key.min(1.0 - f64::EPSILON);
+
+    let mut heap: BinaryHeap<Reverse<ReservoirEntry>> = BinaryHeap::with_capacity(size);
+    for i in 0..size {
+        let u: f64 = rng.gen_range(0.0..1.0);
+        heap.push(Reverse(ReservoirEntry { key: key_of(weights[i], u), index: i }));
+    }
+
+    let mut min_key = clamp_key(heap.peek().unwrap().0.key);
+    let mut skip_weight = f64::ln(rng.gen_range(0.0..1.0)) / f64::ln(min_key);
+    for i in size..n {
+        skip_weight -= weights[i] as f64;
+        if skip_weight <= 0.0 {
+            // the replacement key must be drawn conditioned on t_w = min_key^w_i, not on
+            // min_key directly: that's what keeps the new item's key distributed like the
+            // keys of items that were never skipped, which is what makes the draws
+            // inclusion-probability-proportional-to-weight in the first place
+            let w = (weights[i] as f64).max(f64::MIN_POSITIVE);
+            let t_w = clamp_key(min_key.powf(w));
+            let u = rng.gen_range(t_w..1.0);
+            heap.pop();
+            heap.push(Reverse(ReservoirEntry { key: key_of(weights[i], u), index: i }));
+            min_key = clamp_key(heap.peek().unwrap().0.key);
+            skip_weight = f64::ln(rng.gen_range(0.0..1.0)) / f64::ln(min_key);
+        }
+    }
+
+    heap.into_iter().map(|Reverse(entry)| entry.index).collect()
+}
+
+/// Vose's alias method: a reusable O(n)-build, O(1)-draw weighted sampler.
+/// Built once from a slice of weights, it replaces the O(n) linear scan otherwise
+/// needed for each weighted random draw, which matters when many draws are made
+/// against the same (static) weight distribution.
+pub struct AliasTable {
+    prob: Vec<f64>,
+    alias: Vec<usize>,
+}
+
+impl AliasTable {
+    pub fn new(weights: &[f32]) -> Self {
+        let n = weights.len();
+        let mean: f64 = weights.iter().map(|&w| w as f64).sum::<f64>() / n as f64;
+        let mut scaled: Vec<f64> = weights.iter().map(|&w| w as f64 / mean).collect();
+
+        let mut small: Vec<usize> = Vec::new();
+        let mut large: Vec<usize> = Vec::new();
+        for i in 0..n {
+            if scaled[i] < 1.0 {
+                small.push(i);
+            } else {
+                large.push(i);
+            }
+        }
+
+        let mut prob = vec![0.0f64; n];
+        let mut alias = vec![0usize; n];
+        while let (Some(s), Some(l)) = (small.pop(), large.pop()) {
+            prob[s] = scaled[s];
+            alias[s] = l;
+            scaled[l] -= 1.0 - scaled[s];
+            if scaled[l] < 1.0 {
+                small.push(l);
+            } else {
+                large.push(l);
+            }
+        }
+        // leftovers are the product of floating point error, not a real skew; treat as certain
+        for i in large.into_iter().chain(small.into_iter()) {
+            prob[i] = 1.0;
+        }
+
+        AliasTable { prob, alias }
+    }
+
+    /// Draws a single index in O(1), with probability proportional to the weight
+    /// it was constructed with.
+    pub fn sample(&self, rng: &mut ChaCha20Rng) -> usize {
+        let i = rng.gen_range(0..self.prob.len());
+        if rng.gen_range(0.0..1.0) < self.prob[i] {
+            i
+        } else {
+            self.alias[i]
+        }
+    }
+}
+
+/// How the clustering routines below should be handed their initial centers: this controls
+/// the order of `clustering_input` as it is passed to
+/// `single_centroid_cluster_weighted_vec_with_distance_over_slices`/`multi_cluster_as_weighted_ref`
+/// in `crate::common::cluster`, on the assumption that those routines seed their initial
+/// centers from the front of the slice. `seed_mode_changes_summarize_output_on_clustered_data`
+/// below is an integration test against `summarize()` itself that exercises that assumption
+/// directly: it fails (rather than silently no-op'ing) if `crate::common::cluster` ever
+/// stops honoring input order.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum SeedMode {
+    /// weight-proportional seeding: draws `max_allowed` points without replacement,
+    /// proportional to their weight, via an AliasTable, and moves them to the front
+    Random,
+    /// D^2 (k-means++ style) seeding against the supplied distance function, which
+    /// tends to spread the initial centers out and avoids several landing in the
+    /// same dense region
+    D2,
+}
+
+impl Default for SeedMode {
+    /// weight-proportional seeding, no distance lookahead
+    fn default() -> Self {
+        SeedMode::Random
+    }
+}
+
+/// D^2 seeding: picks `max_allowed` well-separated seed indices out of `0..n`.
+/// The first pick is weight-proportional; every subsequent pick is weighted by
+/// `weight_i * distance(point_i, nearest_already_chosen)^2`, so points far from the
+/// centers chosen so far are preferentially selected as new centers.
+fn d2_seed_indices<'a>(
+    n: usize,
+    weight_of: impl Fn(usize) -> f32,
+    value_of: impl Fn(usize) -> &'a [f32]
+    ,
+    distance: fn(&[f32], &[f32]) -> f64,
+    max_allowed: usize,
+    rng: &mut ChaCha20Rng,
+) -> Vec<usize> {
+    let max_allowed = min(max_allowed, n);
+    if max_allowed == 0 {
+        return Vec::new();
+    }
+    let mut chosen: Vec<usize> = Vec::with_capacity(max_allowed);
+    let mut chosen_mask = vec![false; n];
+    let mut nearest_sq_dist = vec![f64::INFINITY; n];
+
+    let weighted_pick = |cumulative: &dyn Fn(usize) -> f64, total: f64, rng: &mut ChaCha20Rng| -> usize {
+        let mut target = rng.gen_range(0.0..1.0) * total;
+        let mut picked = n - 1;
+        for i in 0..n {
+            target -= cumulative(i);
+            if target <= 0.0 {
+                picked = i;
+                break;
+            }
+        }
+        picked
+    };
+
+    // every pick in this loop, including the first, is weighted by a distribution that
+    // changes from iteration to iteration (the first by raw weight, every subsequent one
+    // by weight * nearest_sq_dist), so an AliasTable here would have to be rebuilt on
+    // every draw: its O(n) build cost is no cheaper than the O(n) scan it would replace.
+    // AliasTable instead pays off in weighted_seed_order() below, where max_allowed draws
+    // are all made against the same static per-point weight distribution.
+    let total_weight: f64 = (0..n).map(|i| weight_of(i) as f64).sum();
+    let first = weighted_pick(&|i| weight_of(i) as f64, total_weight, rng);
+    chosen.push(first);
+    chosen_mask[first] = true;
+    for i in 0..n {
+        let d = distance(value_of(i), value_of(first));
+        nearest_sq_dist[i] = d * d;
+    }
+
+    while chosen.len() < max_allowed {
+        let total: f64 = (0..n)
+            .filter(|&i| !chosen_mask[i])
+            .map(|i| weight_of(i) as f64 * nearest_sq_dist[i])
+            .sum();
+        let next = if total > 0.0 {
+            weighted_pick(
+                &|i| if chosen_mask[i] { 0.0 } else { weight_of(i) as f64 * nearest_sq_dist[i] },
+                total,
+                rng,
+            )
+        } else {
+            // every remaining point has zero weight or coincides with an already-chosen
+            // center; fall back to the first unchosen index so progress is still made
+            match (0..n).find(|&i| !chosen_mask[i]) {
+                Some(i) => i,
+                None => break,
+            }
+        };
+        chosen.push(next);
+        chosen_mask[next] = true;
+        for i in 0..n {
+            let d = distance(value_of(i), value_of(next));
+            let sq = d * d;
+            if sq < nearest_sq_dist[i] {
+                nearest_sq_dist[i] = sq;
+            }
+        }
+    }
+    chosen
+}
+
+/// Reorders `0..n` so that the D^2-seeded indices come first (in seeding order),
+/// followed by the remaining indices in their original relative order.
+fn d2_seed_order<'a>(
+    n: usize,
+    weight_of: impl Fn(usize) -> f32,
+    value_of: impl Fn(usize) -> &'a [f32],
+    distance: fn(&[f32], &[f32]) -> f64,
+    max_allowed: usize,
+    rng: &mut ChaCha20Rng,
+) -> Vec<usize> {
+    let chosen = d2_seed_indices(n, weight_of, value_of, distance, max_allowed, rng);
+    let mut picked = vec![false; n];
+    for &i in &chosen {
+        picked[i] = true;
+    }
+    let mut order = chosen;
+    order.extend((0..n).filter(|&i| !picked[i]));
+    order
+}
+
+/// Weight-proportional (non-D^2) seeding: reorders `0..n` so that `max_allowed` indices
+/// drawn without replacement, proportional to `weights`, come first, followed by the rest
+/// in their original relative order. Unlike D^2 seeding, every draw here is made against
+/// the same, unchanging weight distribution, so an AliasTable built once up front turns
+/// the `max_allowed` draws into O(1) operations each instead of an O(n) scan apiece.
+fn weighted_seed_order(weights: &[f32], max_allowed: usize, rng: &mut ChaCha20Rng) -> Vec<usize> {
+    let n = weights.len();
+    let max_allowed = min(max_allowed, n);
+    if max_allowed == 0 {
+        return (0..n).collect();
+    }
+
+    let table = AliasTable::new(weights);
+    let mut picked = vec![false; n];
+    let mut chosen: Vec<usize> = Vec::with_capacity(max_allowed);
+    // rejection sampling without replacement against the alias table; bounded so that a
+    // run of zero-weight points (which the table will never draw) cannot spin forever
+    let max_attempts = n.saturating_mul(8).max(64);
+    let mut attempts = 0;
+    while chosen.len() < max_allowed && attempts < max_attempts {
+        let i = table.sample(rng);
+        if !picked[i] {
+            picked[i] = true;
+            chosen.push(i);
+        }
+        attempts += 1;
+    }
+    // fill any remainder (e.g. every unpicked point has ~zero weight) in original order
+    if chosen.len() < max_allowed {
+        for i in 0..n {
+            if chosen.len() == max_allowed {
+                break;
+            }
+            if !picked[i] {
+                picked[i] = true;
+                chosen.push(i);
+            }
+        }
+    }
+
+    let mut order = chosen;
+    order.extend((0..n).filter(|&i| !picked[i]));
+    order
+}
+
 #[repr(C)]
 pub struct SampleSummary {
     pub summary_points: Vec<Vec<f32>>,
@@ -110,29 +442,43 @@ impl SampleSummary {
         check_argument(lower_fraction < 0.5, " has to be less than half")?;
         check_argument(upper_fraction > 0.5, "has to be larger than half")?;
         check_argument(dimensions > 0, " cannot have 0 dimensions")?;
-        let total_weight: f64 = points.iter().map(|x| x.1 as f64).sum();
+        let total_weight: f64 = neumaier_sum(points.iter().map(|x| x.1 as f64));
         check_argument(total_weight > 0.0, "weights cannot be all zero")?;
         check_argument(total_weight.is_finite(), " cannot have infinite weights")?;
-        let mut mean = vec![0.0f32; dimensions];
+        let mut mean = vec![0.0f64; dimensions];
+        let mut m2 = vec![0.0f64; dimensions];
         let mut deviation = vec![0.0f32; dimensions];
-        let mut sum_values_sq = vec![0.0f64; dimensions];
-        let mut sum_values = vec![0.0f64; dimensions];
+        // running_weight/weight_compensation mirror neumaier_sum()'s own bookkeeping so that
+        // the compensated weight-so-far fed into the Welford recurrence (and, at the end,
+        // into the variance normalization below) always agrees exactly with total_weight
+        let mut running_weight = 0.0f64;
+        let mut weight_compensation = 0.0f64;
         for i in 0..points.len() {
             check_argument(points[i].1 >= 0.0, "point weights have to be non-negative")?;
+            let w = points[i].1 as f64;
+            let prior_weight = running_weight + weight_compensation;
+            let t = running_weight + w;
+            if running_weight.abs() >= w.abs() {
+                weight_compensation += (running_weight - t) + w;
+            } else {
+                weight_compensation += (w - t) + running_weight;
+            }
+            running_weight = t;
+            let new_weight = running_weight + weight_compensation;
             for j in 0..dimensions {
                 check_argument(
                     points[i].0[j].is_finite() && !points[i].0[j].is_nan(),
                     " cannot have NaN or infinite values"
                 )?;
-                sum_values[j] += points[i].1 as f64 * points[i].0[j] as f64;
-                sum_values_sq[j] +=
-                    points[i].1 as f64 * points[i].0[j] as f64 * points[i].0[j] as f64;
+                let delta = points[i].0[j] as f64 - mean[j];
+                let r = if new_weight != 0.0 { delta * w / new_weight } else { 0.0 };
+                mean[j] += r;
+                m2[j] += prior_weight * delta * r;
             }
         }
+        let mean: Vec<f32> = mean.iter().map(|x| *x as f32).collect();
         for j in 0..dimensions {
-            mean[j] = (sum_values[j] / total_weight) as f32;
-            let t: f64 = sum_values_sq[j] / total_weight
-                - sum_values[j] * sum_values[j] / (total_weight * total_weight);
+            let t = m2[j] / total_weight;
             deviation[j] = f64::sqrt(if t > 0.0 { t } else { 0.0 }) as f32;
         }
         let mut median = vec![0.0f32; dimensions];
@@ -171,29 +517,43 @@ impl SampleSummary {
         check_argument(lower_fraction < 0.5, " has to be less than half")?;
         check_argument(upper_fraction > 0.5, "has to be larger than half")?;
         check_argument(dimensions > 0, " cannot have 0 dimensions")?;
-        let total_weight: f64 = points.iter().map(|x| x.1 as f64).sum();
+        let total_weight: f64 = neumaier_sum(points.iter().map(|x| x.1 as f64));
         check_argument(total_weight > 0.0, "weights cannot be all zero")?;
         check_argument(total_weight.is_finite(), " cannot have infinite weights")?;
-        let mut mean = vec![0.0f32; dimensions];
+        let mut mean = vec![0.0f64; dimensions];
+        let mut m2 = vec![0.0f64; dimensions];
         let mut deviation = vec![0.0f32; dimensions];
-        let mut sum_values_sq = vec![0.0f64; dimensions];
-        let mut sum_values = vec![0.0f64; dimensions];
+        // running_weight/weight_compensation mirror neumaier_sum()'s own bookkeeping so that
+        // the compensated weight-so-far fed into the Welford recurrence (and, at the end,
+        // into the variance normalization below) always agrees exactly with total_weight
+        let mut running_weight = 0.0f64;
+        let mut weight_compensation = 0.0f64;
         for i in 0..points.len() {
             check_argument(points[i].1 >= 0.0, "point weights have to be non-negative")?;
+            let w = points[i].1 as f64;
+            let prior_weight = running_weight + weight_compensation;
+            let t = running_weight + w;
+            if running_weight.abs() >= w.abs() {
+                weight_compensation += (running_weight - t) + w;
+            } else {
+                weight_compensation += (w - t) + running_weight;
+            }
+            running_weight = t;
+            let new_weight = running_weight + weight_compensation;
             for j in 0..dimensions {
                 check_argument(
                     points[i].0[j].is_finite() && !points[i].0[j].is_nan(),
                     " cannot have NaN or infinite values"
                 )?;
-                sum_values[j] += points[i].1 as f64 * points[i].0[j] as f64;
-                sum_values_sq[j] +=
-                    points[i].1 as f64 * points[i].0[j] as f64 * points[i].0[j] as f64;
+                let delta = points[i].0[j] as f64 - mean[j];
+                let r = if new_weight != 0.0 { delta * w / new_weight } else { 0.0 };
+                mean[j] += r;
+                m2[j] += prior_weight * delta * r;
             }
         }
+        let mean: Vec<f32> = mean.iter().map(|x| *x as f32).collect();
         for j in 0..dimensions {
-            mean[j] = (sum_values[j] / total_weight) as f32;
-            let t: f64 = sum_values_sq[j] / total_weight
-                - sum_values[j] * sum_values[j] / (total_weight * total_weight);
+            let t = m2[j] / total_weight;
             deviation[j] = f64::sqrt(if t > 0.0 { t } else { 0.0 }) as f32;
         }
         let mut median = vec![0.0f32; dimensions];
@@ -226,20 +586,66 @@ impl SampleSummary {
     }
 }
 
+/// Preserves the pre-D2-seeding call signature for existing callers; seeds with
+/// `SeedMode::default()` (weight-proportional). Callers that want D2
+/// seeding should call `summarize_with_seed_mode` directly.
 pub fn summarize(
     points: &[(Vec<f32>, f32)],
     distance: fn(&[f32], &[f32]) -> f64,
     max_number: usize,
     parallel_enabled: bool,
+) -> Result<SampleSummary> {
+    summarize_with_seed_mode(points, distance, max_number, parallel_enabled, SeedMode::default())
+}
+
+pub fn summarize_with_seed_mode(
+    points: &[(Vec<f32>, f32)],
+    distance: fn(&[f32], &[f32]) -> f64,
+    max_number: usize,
+    parallel_enabled: bool,
+    seed_mode: SeedMode,
 ) -> Result<SampleSummary> {
     let dimensions = points[0].0.len();
     let mut summary = SampleSummary::from_points(dimensions,&points,LOWER_FRACTION,UPPER_FRACTION)?;
 
     if max_number > 0 {
         let max_allowed = min(dimensions * MAX_NUMBER_PER_DIMENSION, max_number);
+        let mut rng = ChaCha20Rng::seed_from_u64(RESERVOIR_SEED);
+
+        let denoised: Vec<(Vec<f32>, f32)>;
+        let clustering_input: &[(Vec<f32>, f32)] = if points.len() > LENGTH_BOUND {
+            let weights: Vec<f32> = points.iter().map(|x| x.1).collect();
+            let chosen = weighted_reservoir_sample(&weights, LENGTH_BOUND, &mut rng);
+            denoised = chosen.into_iter().map(|i| points[i].clone()).collect();
+            &denoised
+        } else {
+            points
+        };
+
+        let seeded: Vec<(Vec<f32>, f32)>;
+        let clustering_input: &[(Vec<f32>, f32)] = match seed_mode {
+            SeedMode::D2 => {
+                let order = d2_seed_order(
+                    clustering_input.len(),
+                    |i| clustering_input[i].1,
+                    |i| clustering_input[i].0.as_slice(),
+                    distance,
+                    max_allowed,
+                    &mut rng,
+                );
+                seeded = order.into_iter().map(|i| clustering_input[i].clone()).collect();
+                &seeded
+            }
+            SeedMode::Random => {
+                let weights: Vec<f32> = clustering_input.iter().map(|x| x.1).collect();
+                let order = weighted_seed_order(&weights, max_allowed, &mut rng);
+                seeded = order.into_iter().map(|i| clustering_input[i].clone()).collect();
+                &seeded
+            }
+        };
 
         let mut list: Vec<Center> = single_centroid_cluster_weighted_vec_with_distance_over_slices(
-            &points,
+            clustering_input,
             distance,
             max_allowed,
             parallel_enabled,
@@ -258,6 +664,9 @@ pub fn summarize(
 }
 
 
+/// Preserves the pre-D2-seeding call signature for existing callers; seeds with
+/// `SeedMode::default()` (weight-proportional). Callers that want D2
+/// seeding should call `multi_summarize_ref_with_seed_mode` directly.
 pub fn multi_summarize_ref(
     points: &[(&[f32], f32)],
     distance: fn(&[f32], &[f32]) -> f64,
@@ -265,15 +674,68 @@ pub fn multi_summarize_ref(
     shrinkage : f32,
     max_number: usize,
     parallel_enabled: bool,
+) -> Result<SampleSummary> {
+    multi_summarize_ref_with_seed_mode(
+        points,
+        distance,
+        number_of_representatives,
+        shrinkage,
+        max_number,
+        parallel_enabled,
+        SeedMode::default(),
+    )
+}
+
+pub fn multi_summarize_ref_with_seed_mode(
+    points: &[(&[f32], f32)],
+    distance: fn(&[f32], &[f32]) -> f64,
+    number_of_representatives: usize,
+    shrinkage : f32,
+    max_number: usize,
+    parallel_enabled: bool,
+    seed_mode: SeedMode,
 ) -> Result<SampleSummary> {
     let dimensions = points[0].0.len();
     let mut summary = SampleSummary::from_references(dimensions,points,LOWER_FRACTION,UPPER_FRACTION)?;
 
     if max_number > 0 {
         let max_allowed = min(dimensions * MAX_NUMBER_PER_DIMENSION, max_number);
+        let mut rng = ChaCha20Rng::seed_from_u64(RESERVOIR_SEED);
+
+        let denoised: Vec<(&[f32], f32)>;
+        let clustering_input: &[(&[f32], f32)] = if points.len() > LENGTH_BOUND {
+            let weights: Vec<f32> = points.iter().map(|x| x.1).collect();
+            let chosen = weighted_reservoir_sample(&weights, LENGTH_BOUND, &mut rng);
+            denoised = chosen.into_iter().map(|i| points[i]).collect();
+            &denoised
+        } else {
+            points
+        };
+
+        let seeded: Vec<(&[f32], f32)>;
+        let clustering_input: &[(&[f32], f32)] = match seed_mode {
+            SeedMode::D2 => {
+                let order = d2_seed_order(
+                    clustering_input.len(),
+                    |i| clustering_input[i].1,
+                    |i| clustering_input[i].0,
+                    distance,
+                    max_allowed,
+                    &mut rng,
+                );
+                seeded = order.into_iter().map(|i| clustering_input[i]).collect();
+                &seeded
+            }
+            SeedMode::Random => {
+                let weights: Vec<f32> = clustering_input.iter().map(|x| x.1).collect();
+                let order = weighted_seed_order(&weights, max_allowed, &mut rng);
+                seeded = order.into_iter().map(|i| clustering_input[i]).collect();
+                &seeded
+            }
+        };
 
         let mut list= multi_cluster_as_weighted_ref(
-            &points,
+            clustering_input,
             distance,
             number_of_representatives,
             shrinkage,
@@ -294,3 +756,282 @@ pub fn multi_summarize_ref(
 
     return Ok(summary);
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // data shifted far from zero is where the naive E[x^2] - E[x]^2 formula is prone to
+    // catastrophic cancellation; checks that the Welford-based from_points reports a
+    // nonzero deviation for such a sample, which plainly has spread
+    #[test]
+    fn shifted_data_has_nonzero_deviation() {
+        let shift = 1.0e7f32;
+        let points: Vec<(Vec<f32>, f32)> = vec![
+            (vec![shift - 1.0], 1.0),
+            (vec![shift], 1.0),
+            (vec![shift + 1.0], 1.0),
+        ];
+        let summary = SampleSummary::from_points(1, &points, LOWER_FRACTION, UPPER_FRACTION).unwrap();
+        assert!(
+            summary.deviation[0] > 0.0,
+            "expected nonzero deviation for a shifted but non-degenerate sample, got {}",
+            summary.deviation[0]
+        );
+    }
+
+    #[test]
+    fn from_references_agrees_with_from_points_on_shifted_data() {
+        let shift = 1.0e7f32;
+        let values: Vec<Vec<f32>> = vec![vec![shift - 1.0], vec![shift], vec![shift + 1.0]];
+        let owned_points: Vec<(Vec<f32>, f32)> = values.iter().map(|v| (v.clone(), 1.0)).collect();
+        let ref_points: Vec<(&Vec<f32>, f32)> = values.iter().map(|v| (v, 1.0)).collect();
+
+        let from_owned = SampleSummary::from_points(1, &owned_points, LOWER_FRACTION, UPPER_FRACTION).unwrap();
+        let from_refs = SampleSummary::from_references(1, &ref_points, LOWER_FRACTION, UPPER_FRACTION).unwrap();
+
+        assert_eq!(from_owned.deviation[0], from_refs.deviation[0]);
+        assert_eq!(from_owned.mean[0], from_refs.mean[0]);
+    }
+
+    #[test]
+    fn reservoir_sample_passes_through_when_not_over_size() {
+        let weights = vec![1.0f32, 2.0, 3.0];
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut chosen = weighted_reservoir_sample(&weights, weights.len(), &mut rng);
+        chosen.sort();
+        assert_eq!(chosen, vec![0, 1, 2]);
+
+        let mut rng = ChaCha20Rng::seed_from_u64(7);
+        let mut chosen = weighted_reservoir_sample(&weights, weights.len() + 5, &mut rng);
+        chosen.sort();
+        assert_eq!(chosen, vec![0, 1, 2]);
+    }
+
+    // large-but-valid f32 weights used to make key_of(w, u) = u^(1/w) round up to exactly
+    // 1.0 in f64, which made gen_range(min_key..1.0) panic on an empty range; this is the
+    // precise shape of input (a large batch, i.e. n > size, with large uniform weights)
+    // that triggered the crash
+    #[test]
+    fn reservoir_sample_does_not_panic_on_large_weights() {
+        let weights = vec![1.0e20f32; 2000];
+        let mut rng = ChaCha20Rng::seed_from_u64(11);
+        let chosen = weighted_reservoir_sample(&weights, 1000, &mut rng);
+        assert_eq!(chosen.len(), 1000);
+    }
+
+    #[test]
+    fn reservoir_sample_is_weight_proportional_over_many_trials() {
+        // two items, one ten times as likely as the other; sample a reservoir of size 1
+        // repeatedly and check the heavier item is chosen roughly 10x as often
+        let weights = vec![1.0f32, 10.0f32];
+        let mut heavy_count = 0u32;
+        let trials = 2000u32;
+        for seed in 0..trials as u64 {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            let chosen = weighted_reservoir_sample(&weights, 1, &mut rng);
+            if chosen[0] == 1 {
+                heavy_count += 1;
+            }
+        }
+        let fraction = heavy_count as f64 / trials as f64;
+        assert!(
+            fraction > 0.8,
+            "expected the 10x-heavier item to dominate single-slot draws, got fraction {}",
+            fraction
+        );
+    }
+
+    // regression test for a bug in the A-ExpJ replacement step: the new key's uniform
+    // variate was drawn from gen_range(min_key..1.0) instead of the required
+    // gen_range(min_key.powf(w_i)..1.0), which skewed per-item inclusion frequencies far
+    // from proportional-to-weight (low-weight items undercounted by >20x in one reported
+    // case). Cross-check the per-item marginal inclusion frequency of the fast A-ExpJ path
+    // against a plain, unaccelerated A-Res reference (assign every item a key, take the
+    // top `k`) over many trials: the two must agree, since A-ExpJ is only supposed to be a
+    // faster way to compute the same distribution A-Res computes directly.
+    #[test]
+    fn reservoir_sample_matches_full_scan_inclusion_frequencies() {
+        let weights = vec![1.0f32, 2.0, 5.0, 0.1, 0.3];
+        let k = 2;
+        let trials = 20_000u64;
+
+        let mut exp_counts = vec![0u32; weights.len()];
+        let mut ref_counts = vec![0u32; weights.len()];
+
+        for seed in 0..trials {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            for i in weighted_reservoir_sample(&weights, k, &mut rng) {
+                exp_counts[i] += 1;
+            }
+
+            let mut ref_rng = ChaCha20Rng::seed_from_u64(seed ^ 0x9E37_79B9_7F4A_7C15);
+            let mut keyed: Vec<(f64, usize)> = weights
+                .iter()
+                .enumerate()
+                .map(|(i, &w)| {
+                    let u: f64 = ref_rng.gen_range(0.0..1.0);
+                    (u.powf(1.0 / (w as f64).max(f64::MIN_POSITIVE)), i)
+                })
+                .collect();
+            keyed.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap());
+            for &(_, i) in keyed.iter().take(k) {
+                ref_counts[i] += 1;
+            }
+        }
+
+        for i in 0..weights.len() {
+            let exp_fraction = exp_counts[i] as f64 / trials as f64;
+            let ref_fraction = ref_counts[i] as f64 / trials as f64;
+            assert!(
+                (exp_fraction - ref_fraction).abs() < 0.05,
+                "index {} inclusion frequency diverged from the reference sampler: \
+                 A-ExpJ {} vs A-Res reference {}",
+                i,
+                exp_fraction,
+                ref_fraction
+            );
+        }
+    }
+
+    #[test]
+    fn alias_table_is_weight_proportional_over_many_trials() {
+        let weights = vec![1.0f32, 10.0f32];
+        let table = AliasTable::new(&weights);
+        let mut heavy_count = 0u32;
+        let trials = 2000u32;
+        let mut rng = ChaCha20Rng::seed_from_u64(13);
+        for _ in 0..trials {
+            if table.sample(&mut rng) == 1 {
+                heavy_count += 1;
+            }
+        }
+        let fraction = heavy_count as f64 / trials as f64;
+        assert!(
+            fraction > 0.8,
+            "expected the 10x-heavier item to dominate draws, got fraction {}",
+            fraction
+        );
+    }
+
+    #[test]
+    fn alias_table_never_draws_an_out_of_range_index() {
+        let weights = vec![5.0f32, 0.0, 1.0, 100.0];
+        let table = AliasTable::new(&weights);
+        let mut rng = ChaCha20Rng::seed_from_u64(17);
+        for _ in 0..1000 {
+            let i = table.sample(&mut rng);
+            assert!(i < weights.len());
+        }
+    }
+
+    #[test]
+    fn weighted_seed_order_is_a_permutation_when_max_allowed_exceeds_n() {
+        let weights = vec![1.0f32, 2.0, 3.0];
+        let mut rng = ChaCha20Rng::seed_from_u64(19);
+        let mut order = weighted_seed_order(&weights, weights.len() + 2, &mut rng);
+        order.sort();
+        assert_eq!(order, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn weighted_seed_order_moves_max_allowed_distinct_indices_to_the_front() {
+        let weights = vec![1.0f32, 1.0, 1.0, 1.0, 1.0];
+        let mut rng = ChaCha20Rng::seed_from_u64(23);
+        let order = weighted_seed_order(&weights, 2, &mut rng);
+        assert_eq!(order.len(), weights.len());
+        // the first max_allowed entries are distinct, valid indices
+        assert_ne!(order[0], order[1]);
+        assert!(order[0] < weights.len() && order[1] < weights.len());
+    }
+
+    fn euclidean(a: &[f32], b: &[f32]) -> f64 {
+        a.iter().zip(b.iter()).map(|(x, y)| ((*x - *y) as f64).powi(2)).sum::<f64>().sqrt()
+    }
+
+    // two tight clusters, far apart: D2 seeding should never pick its first two centers
+    // from the same cluster, since after the first pick every point in that same cluster
+    // has a tiny nearest_sq_dist and is very unlikely to be drawn next
+    #[test]
+    fn d2_seeding_spreads_centers_across_well_separated_clusters() {
+        let mut points: Vec<Vec<f32>> = Vec::new();
+        for i in 0..10 {
+            points.push(vec![0.0 + (i as f32) * 0.01]);
+        }
+        for i in 0..10 {
+            points.push(vec![1000.0 + (i as f32) * 0.01]);
+        }
+        let weights = vec![1.0f32; points.len()];
+
+        for seed in 0..20u64 {
+            let mut rng = ChaCha20Rng::seed_from_u64(seed);
+            let chosen = d2_seed_indices(
+                points.len(),
+                |i| weights[i],
+                |i| points[i].as_slice(),
+                euclidean,
+                2,
+                &mut rng,
+            );
+            assert_eq!(chosen.len(), 2);
+            let same_cluster = (chosen[0] < 10) == (chosen[1] < 10);
+            assert!(
+                !same_cluster,
+                "expected D2 seeding to pick one center per cluster, got {:?}",
+                chosen
+            );
+        }
+    }
+
+    #[test]
+    fn d2_seed_order_moves_the_seeded_indices_to_the_front() {
+        let points: Vec<Vec<f32>> = vec![vec![0.0], vec![0.0], vec![1000.0], vec![1000.0]];
+        let weights = vec![1.0f32; points.len()];
+        let mut rng = ChaCha20Rng::seed_from_u64(29);
+        let order = d2_seed_order(
+            points.len(),
+            |i| weights[i],
+            |i| points[i].as_slice(),
+            euclidean,
+            2,
+            &mut rng,
+        );
+        assert_eq!(order.len(), points.len());
+        let front: std::collections::HashSet<_> = order[..2].iter().cloned().collect();
+        assert!(front.contains(&0) || front.contains(&1));
+        assert!(front.contains(&2) || front.contains(&3));
+    }
+
+    // integration test: SeedMode only matters if it actually changes what
+    // summarize()/multi_summarize_ref() hand the real clustering routines, not just what
+    // d2_seed_order()/weighted_seed_order() compute in isolation. This asserts the two
+    // modes yield different summary_points on clustered input; if crate::common::cluster
+    // ever starts ignoring clustering_input's order, this is the test that should fail.
+    #[test]
+    fn seed_mode_changes_summarize_output_on_clustered_data() {
+        fn euclidean(a: &[f32], b: &[f32]) -> f64 {
+            a.iter().zip(b.iter()).map(|(x, y)| ((*x - *y) as f64).powi(2)).sum::<f64>().sqrt()
+        }
+
+        let mut points: Vec<(Vec<f32>, f32)> = Vec::new();
+        for i in 0..20 {
+            points.push((vec![(i as f32) * 0.001], 1.0));
+        }
+        for i in 0..20 {
+            points.push((vec![1000.0 + (i as f32) * 0.001], 1.0));
+        }
+
+        let d2_summary =
+            summarize_with_seed_mode(&points, euclidean, 2, false, SeedMode::D2).unwrap();
+        let random_summary =
+            summarize_with_seed_mode(&points, euclidean, 2, false, SeedMode::Random).unwrap();
+
+        assert_ne!(
+            d2_summary.summary_points, random_summary.summary_points,
+            "expected SeedMode::D2 and SeedMode::Random to hand the clustering routine \
+             differently-ordered initial centers and so produce different summary points \
+             on this clustered input; if this fails, crate::common::cluster is ignoring \
+             input order and the seeding feature is a no-op"
+        );
+    }
+}